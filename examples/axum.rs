@@ -0,0 +1,21 @@
+//!
+//! Serves the minimal Axum app from the playground.
+//!
+//! ```bash
+//! cargo run --example axum
+//! ```
+//!
+
+use rust_web::{config::AppConfig, playground, server};
+
+#[tokio::main]
+async fn main() {
+    server::init_tracing();
+
+    let config = AppConfig::load().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1)
+    });
+
+    playground::example_axum(&config).await;
+}
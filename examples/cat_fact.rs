@@ -0,0 +1,21 @@
+//!
+//! Serves the cat fact app from the clients section (EXERCISE 1).
+//!
+//! ```bash
+//! cargo run --example cat_fact
+//! ```
+//!
+
+use rust_web::{client, config::AppConfig, server};
+
+#[tokio::main]
+async fn main() {
+    server::init_tracing();
+
+    let config = AppConfig::load().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1)
+    });
+
+    client::cat_fact_server(&config).await;
+}
@@ -0,0 +1,21 @@
+//!
+//! Serves the "hello world" app from the basics section.
+//!
+//! ```bash
+//! cargo run --example hello_world
+//! ```
+//!
+
+use rust_web::{basics, config::AppConfig, server};
+
+#[tokio::main]
+async fn main() {
+    server::init_tracing();
+
+    let config = AppConfig::load().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1)
+    });
+
+    basics::hello_world(&config).await;
+}
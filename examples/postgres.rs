@@ -0,0 +1,17 @@
+//!
+//! Starts Postgres in a Docker container and runs a query against it, as in
+//! the playground. Requires a running Docker daemon.
+//!
+//! ```bash
+//! cargo run --example postgres
+//! ```
+//!
+
+use rust_web::{playground, server};
+
+#[tokio::main]
+async fn main() {
+    server::init_tracing();
+
+    playground::example_postgres().await.unwrap();
+}
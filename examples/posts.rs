@@ -0,0 +1,21 @@
+//!
+//! Serves the JSONPlaceholder posts proxy from the clients section (EXERCISE 2).
+//!
+//! ```bash
+//! cargo run --example posts
+//! ```
+//!
+
+use rust_web::{client, config::AppConfig, server};
+
+#[tokio::main]
+async fn main() {
+    server::init_tracing();
+
+    let config = AppConfig::load().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1)
+    });
+
+    client::posts_server(&config).await;
+}
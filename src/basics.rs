@@ -23,28 +23,34 @@ use axum::{
     Json, Router,
 };
 
+use crate::config::AppConfig;
+
 ///
 /// In this "hello world" example, you can see the core elements of an Axum
 /// web application:
 ///
-/// 1. A router, which is used for specifying routes.
+/// 1. A router (see `hello_world_router`), which is used for specifying routes.
 /// 2. A single route, defined with a path and a handler.
 /// 3. A handler, which is an asynchronous function that returns a response.
 /// 4. A listener, which is used to listen for incoming connections.
-/// 5. A call to `axum::serve`, which starts the server.
+/// 5. A call to `axum::serve` (here via `server::serve`, which also adds the
+///    workshop's request ids and panic handling, and lets in-flight requests
+///    finish when you stop the server with Ctrl+C).
 ///
-pub async fn hello_world() {
+pub async fn hello_world(config: &AppConfig) {
     // build our application with a route
-    let app = Router::new().route("/", get(handler));
+    let app = hello_world_router();
 
     // run it
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:3000")
+    let listener = tokio::net::TcpListener::bind(config.bind_addr)
         .await
         .unwrap();
 
-    println!("Listening on {}", listener.local_addr().unwrap());
+    crate::server::serve(listener, app, config).await.unwrap();
+}
 
-    axum::serve(listener, app).await.unwrap();
+pub fn hello_world_router() -> Router {
+    Router::new().route("/", get(handler))
 }
 
 ///
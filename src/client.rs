@@ -22,6 +22,8 @@ use axum::{
     Json, Router,
 };
 
+use crate::config::AppConfig;
+
 ///
 /// EXERCISE 1
 ///
@@ -38,16 +40,17 @@ use axum::{
 ///
 ///
 ///
-pub async fn cat_fact_server() {
-    let app = Router::<()>::new().route("/", get(cat_fact_handler));
+pub async fn cat_fact_server(config: &AppConfig) {
+    let app = cat_fact_router();
 
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:3000")
+    let listener = tokio::net::TcpListener::bind(config.bind_addr)
         .await
         .unwrap();
 
-    println!("Listening on {}", listener.local_addr().unwrap());
-
-    axum::serve(listener, app).await.unwrap();
+    crate::server::serve(listener, app, config).await.unwrap();
+}
+pub fn cat_fact_router() -> Router {
+    Router::<()>::new().route("/", get(cat_fact_handler))
 }
 async fn cat_fact_handler() -> Html<String> {
     todo!("Using reqwest::get and .json, get a random cat fact from https://catfact.ninja/fact and return it as an HTML response.")
@@ -90,21 +93,24 @@ struct CatFact {
 /// to for interacting with the API using Reqwest.
 ///
 /// You will have to use use a Reqwest client to make requests to the API.
-/// One has been provided for you in the `posts_server` function. You can
+/// One has been provided for you in the `posts_router` function. You can
 /// set the body of a request using the `.body` method.`
 ///
-async fn posts_server() {
-    let app = Router::<()>::new();
-
-    let _client = reqwest::Client::new();
+pub async fn posts_server(config: &AppConfig) {
+    let app = posts_router();
 
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:3000")
+    let listener = tokio::net::TcpListener::bind(config.bind_addr)
         .await
         .unwrap();
 
-    println!("Listening on {}", listener.local_addr().unwrap());
+    crate::server::serve(listener, app, config).await.unwrap();
+}
+pub fn posts_router() -> Router {
+    let app = Router::<()>::new();
+
+    let _client = reqwest::Client::new();
 
-    axum::serve(listener, app).await.unwrap();
+    app
 }
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
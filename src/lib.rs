@@ -0,0 +1,24 @@
+//!
+//! RUST WEB
+//! --------
+//!
+//! The workshop material, one module per section, plus the plumbing shared by
+//! the runnable servers (`config` and `server`). Each runnable server has an
+//! entry in `examples/`, so you can start it with, for example:
+//!
+//! ```bash
+//! cargo run --example hello_world
+//! ```
+//!
+
+mod architecture;
+pub mod basics;
+pub mod client;
+pub mod config;
+mod context;
+mod handlers;
+mod middleware;
+mod persistence;
+pub mod playground;
+pub mod server;
+mod welcome;
@@ -1,17 +1,9 @@
-mod architecture;
-mod basics;
-mod client;
-mod context;
-mod handlers;
-mod middleware;
-mod persistence;
-mod playground;
-mod welcome;
-
 #[tokio::main]
 async fn main() {
-    // playground::example_postgres().await.unwrap();
-    // basics::hello_world().await;
+    // The runnable servers live in `examples/`, for instance:
+    //
+    //   cargo run --example hello_world
+    //   cargo run --example postgres
 
     println!("Hello, world!");
 }
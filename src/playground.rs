@@ -3,6 +3,8 @@
 use axum::{response::Html, routing::*, Router};
 use sqlx::postgres::PgPoolOptions;
 
+use crate::config::AppConfig;
+
 ///
 /// This example shows you how to use the `testcontainers` crate to run a
 /// Postgres database in a Docker container, and how to make basic queries
@@ -33,16 +35,18 @@ pub async fn example_postgres() -> Result<(), sqlx::Error> {
     Ok(())
 }
 
-pub async fn example_axum() {
+pub fn example_axum_router() -> Router {
+    Router::new().route("/", get(|| async move { Html("Hello, World!") }))
+}
+
+pub async fn example_axum(config: &AppConfig) {
     // build our application with a route
-    let app = Router::new().route("/", get(|| async move { Html("Hello, World!") }));
+    let app = example_axum_router();
 
     // run it
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:3000")
+    let listener = tokio::net::TcpListener::bind(config.bind_addr)
         .await
         .unwrap();
 
-    println!("Listening on {}", listener.local_addr().unwrap());
-
-    axum::serve(listener, app).await.unwrap();
+    crate::server::serve(listener, app, config).await.unwrap();
 }
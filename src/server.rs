@@ -0,0 +1,698 @@
+//!
+//! SERVER
+//! ------
+//!
+//! Plumbing shared by the runnable servers in this workshop. Nothing in this
+//! module is an exercise: it exists so that the servers you start from `main`
+//! behave like well-mannered production services, from tagging each request
+//! through to shutting down.
+//!
+//! Events are logged with `tracing`; the examples print them by calling
+//! `init_tracing`.
+//!
+
+use axum::{
+    extract::Request,
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use futures_util::FutureExt;
+use std::{
+    any::Any,
+    backtrace::Backtrace,
+    cell::{Cell, RefCell},
+    future::{poll_fn, Future, IntoFuture},
+    io,
+    panic::AssertUnwindSafe,
+    pin::pin,
+    sync::Once,
+    task::Poll,
+    time::Duration,
+};
+use tokio::{net::TcpListener, sync::oneshot};
+use tower::ServiceBuilder;
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, RequestId, SetRequestIdLayer},
+    trace::TraceLayer,
+};
+use tracing::Span;
+use tracing_subscriber::{filter::LevelFilter, EnvFilter};
+
+use crate::config::AppConfig;
+
+/// The header used to carry request ids, both inbound and outbound.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+///
+/// Prints `tracing` events to stdout, filtered by `RUST_LOG` (for instance
+/// `RUST_LOG=debug` or `RUST_LOG=rust_web=debug,tower_http=debug`), and at
+/// `info` and above when it is unset.
+///
+pub fn init_tracing() {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::builder()
+                .with_default_directive(LevelFilter::INFO.into())
+                .from_env_lossy(),
+        )
+        .init();
+}
+
+///
+/// Wraps `router` in the layers every workshop server gets; `serve` applies it
+/// for you, so the exercise routers stay plain.
+///
+/// - Every request gets an `x-request-id`, generated as a UUID unless the
+///   client already sent one, which is echoed back on the response. The id is
+///   also stored in the request extensions, so handlers can take an
+///   `Extension<RequestId>` parameter to read it, e.g. to pass it along with
+///   `forward_request_id`.
+/// - Each request is handled in a `tracing` span that records its method,
+///   URI and request id, so every event logged for it can be correlated.
+/// - A panicking handler becomes a JSON 500 (see `catch_panic`).
+///
+pub fn with_defaults(router: Router) -> Router {
+    // listed outermost first: the trace span and the panic handler have to
+    // sit inside `SetRequestIdLayer`, so that they can see the id
+    router.layer(
+        ServiceBuilder::new()
+            .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+            .layer(TraceLayer::new_for_http().make_span_with(request_span))
+            .layer(PropagateRequestIdLayer::x_request_id())
+            .layer(axum::middleware::from_fn(catch_panic)),
+    )
+}
+
+/// The span each request is handled in; events logged while handling it,
+/// including from handlers, carry its `request_id`.
+fn request_span(request: &Request) -> Span {
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .and_then(|id| id.header_value().to_str().ok())
+        .unwrap_or("unknown");
+
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        request_id = %request_id,
+    )
+}
+
+///
+/// Copies the id of the request being handled onto an outbound `reqwest`
+/// request, so that the downstream service's logs can be correlated with ours.
+///
+pub fn forward_request_id(
+    request: reqwest::RequestBuilder,
+    id: &RequestId,
+) -> reqwest::RequestBuilder {
+    // reqwest is still on http 0.2, so the value is copied across as raw bytes;
+    // both versions accept exactly the same header value bytes.
+    let id = reqwest::header::HeaderValue::from_bytes(id.header_value().as_bytes())
+        .expect("a valid http 1.x header value is a valid http 0.2 one");
+
+    request.header(REQUEST_ID_HEADER, id)
+}
+
+///
+/// Middleware, for use with `axum::middleware::from_fn`, that turns a panic in
+/// any handler beneath it into a JSON 500 response instead of a dropped
+/// connection. Handlers are full of `unwrap()`s and `todo!()`s while you work
+/// through the exercises, so this keeps the server answering.
+///
+/// The panic is logged as a `tracing` error carrying the request id, the
+/// panic location, and a backtrace, in place of Rust's default panic report.
+///
+/// `with_defaults` installs it inside the request-id layers, so the response
+/// can quote the id of the request that panicked.
+///
+pub async fn catch_panic(request: Request, next: Next) -> Response {
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .and_then(|id| id.header_value().to_str().ok())
+        .map(str::to_string);
+
+    let mut response = pin!(AssertUnwindSafe(next.run(request)).catch_unwind());
+
+    let result = poll_fn(|cx| {
+        CAUGHT_PANIC.take();
+
+        let outer = CATCHING_PANICS.replace(true);
+        let poll = response.as_mut().poll(cx);
+        CATCHING_PANICS.set(outer);
+
+        // a panic recorded during a poll that did not end in one was caught
+        // further down, e.g. by the handler; report it here, since the hook
+        // kept it from being printed, and don't let it outlive this poll
+        if !matches!(poll, Poll::Ready(Err(_))) {
+            if let Some(caught) = CAUGHT_PANIC.take() {
+                tracing::warn!(
+                    request_id = request_id.as_deref().unwrap_or("unknown"),
+                    location = %caught.location,
+                    backtrace = %caught.backtrace,
+                    "Handler caught a panic of its own: {}",
+                    caught.message
+                );
+            }
+        }
+
+        poll
+    })
+    .await;
+
+    match result {
+        Ok(response) => response,
+        Err(panic) => {
+            let (location, backtrace) = match CAUGHT_PANIC.take() {
+                Some(caught) => (caught.location, caught.backtrace.to_string()),
+                None => ("unknown".to_string(), "not captured".to_string()),
+            };
+
+            tracing::error!(
+                request_id = request_id.as_deref().unwrap_or("unknown"),
+                %location,
+                %backtrace,
+                "Handler panicked: {}",
+                panic_message(&*panic)
+            );
+
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": "internal server error",
+                    "request_id": request_id,
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// A panic raised while `catch_panic` was polling, as recorded by the hook.
+struct CaughtPanic {
+    message: String,
+    location: String,
+    backtrace: Backtrace,
+}
+
+thread_local! {
+    /// Whether `catch_panic` is polling a handler on this thread right now.
+    static CATCHING_PANICS: Cell<bool> = const { Cell::new(false) };
+    /// The panic raised during the current `catch_panic` poll on this thread.
+    static CAUGHT_PANIC: RefCell<Option<CaughtPanic>> = const { RefCell::new(None) };
+}
+
+///
+/// Installs a panic hook that records the message, location and a backtrace
+/// of panics raised while `catch_panic` polls a handler, for it to log along
+/// with the request id. Any other panic is passed on to the hook that was
+/// installed before, so it is reported as usual. Only the first call has an
+/// effect.
+///
+/// `serve` calls this; call it yourself if you use `with_defaults` without
+/// `serve`, or panics are logged without a location or backtrace.
+///
+/// A panic unwinds on the thread that polled the handler, which is the thread
+/// `catch_panic` inspects afterwards, so thread-locals are enough here.
+///
+pub fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+
+        std::panic::set_hook(Box::new(move |info| {
+            if CATCHING_PANICS.get() {
+                CAUGHT_PANIC.set(Some(CaughtPanic {
+                    message: panic_message(info.payload()).to_string(),
+                    location: info
+                        .location()
+                        .map_or_else(|| "unknown".to_string(), ToString::to_string),
+                    backtrace: Backtrace::force_capture(),
+                }));
+            } else {
+                previous(info);
+            }
+        }));
+    });
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message
+    } else {
+        "<non-string panic payload>"
+    }
+}
+
+///
+/// Serves `app`, wrapped in `with_defaults`, on `listener` until the process
+/// is asked to stop (see `shutdown_signal`), then shuts down gracefully: new
+/// connections are refused and in-flight requests are given
+/// `config.shutdown_timeout` to drain.
+///
+/// If some are still running after that, this returns an error of kind
+/// `TimedOut` without waiting for them, so a stuck request cannot block a
+/// deployment; they are cancelled along with the runtime when `main` returns.
+/// `Ok` means every request finished.
+///
+pub async fn serve(listener: TcpListener, app: Router, config: &AppConfig) -> io::Result<()> {
+    install_panic_hook();
+
+    tracing::info!("Listening on {}", listener.local_addr()?);
+
+    serve_until(
+        listener,
+        with_defaults(app),
+        shutdown_signal(),
+        config.shutdown_timeout,
+    )
+    .await
+}
+
+async fn serve_until(
+    listener: TcpListener,
+    app: Router,
+    signal: impl Future<Output = ()> + Send + 'static,
+    timeout: Duration,
+) -> io::Result<()> {
+    let (signalled, on_signal) = oneshot::channel::<()>();
+
+    let server = axum::serve(listener, app).with_graceful_shutdown(async move {
+        signal.await;
+
+        tracing::info!("Shutting down, draining in-flight requests for up to {timeout:?}");
+
+        let _ = signalled.send(());
+    });
+
+    let deadline = async move {
+        match on_signal.await {
+            Ok(()) => tokio::time::sleep(timeout).await,
+            // the server stopped without being signalled
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+
+    tokio::select! {
+        result = server.into_future() => result,
+        _ = deadline => Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!("in-flight requests did not drain within {timeout:?}"),
+        )),
+    }
+}
+
+///
+/// Completes when the process receives Ctrl+C (SIGINT) or, on Unix, SIGTERM.
+///
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+/// Log output collected by a test-local subscriber, so tests can check it.
+#[cfg(test)]
+#[derive(Clone, Default)]
+struct CapturedLogs(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+#[cfg(test)]
+impl CapturedLogs {
+    /// Routes this thread's `tracing` events into the returned logs until the
+    /// guard is dropped.
+    fn install() -> (Self, tracing::subscriber::DefaultGuard) {
+        let logs = Self::default();
+        let guard = tracing::subscriber::set_default(
+            tracing_subscriber::fmt()
+                .with_ansi(false)
+                .with_writer({
+                    let logs = logs.clone();
+                    move || logs.clone()
+                })
+                .finish(),
+        );
+
+        (logs, guard)
+    }
+
+    fn contents(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+#[cfg(test)]
+impl io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn request_id_is_generated_and_echoed() {
+    use axum::{body::Body, extract::Extension, http::Request, routing::get, Router};
+    // for Body::collect
+    use http_body_util::BodyExt;
+    /// for ServiceExt::oneshot
+    use tower::util::ServiceExt;
+
+    let app = with_defaults(Router::new().route(
+        "/",
+        get(|Extension(id): Extension<RequestId>| async move {
+            id.header_value().to_str().unwrap().to_string()
+        }),
+    ));
+
+    let response = app
+        .clone()
+        .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    let generated = response.headers().get(REQUEST_ID_HEADER).unwrap().clone();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    assert_eq!(generated.len(), 36);
+    assert_eq!(body, generated.as_bytes());
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/")
+                .header(REQUEST_ID_HEADER, "abc-123")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        response.headers().get(REQUEST_ID_HEADER).unwrap(),
+        "abc-123"
+    );
+}
+
+#[tokio::test]
+async fn events_logged_by_handlers_carry_the_request_id() {
+    use axum::{body::Body, http::Request, routing::get, Router};
+    /// for ServiceExt::oneshot
+    use tower::util::ServiceExt;
+
+    let (logs, _subscriber) = CapturedLogs::install();
+
+    let app = with_defaults(Router::new().route(
+        "/",
+        get(|| async {
+            tracing::info!("handling the request");
+        }),
+    ));
+
+    app.oneshot(
+        Request::builder()
+            .uri("/")
+            .header(REQUEST_ID_HEADER, "abc-123")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    let logs = logs.contents();
+    let line = logs
+        .lines()
+        .find(|line| line.contains("handling the request"))
+        .unwrap();
+
+    assert!(line.contains("request_id=abc-123"), "{line}");
+}
+
+#[test]
+fn request_ids_are_forwarded_to_outbound_requests() {
+    use axum::http::HeaderValue;
+
+    let forward = |id: HeaderValue| {
+        forward_request_id(
+            reqwest::Client::new().get("http://localhost/"),
+            &RequestId::new(id),
+        )
+        .build()
+        .unwrap()
+    };
+
+    let request = forward(HeaderValue::from_static("abc-123"));
+
+    assert_eq!(request.headers().get(REQUEST_ID_HEADER).unwrap(), "abc-123");
+
+    // not valid UTF-8, but still a legal header value
+    let request = forward(HeaderValue::from_bytes(b"caf\xe9").unwrap());
+
+    assert_eq!(
+        request.headers().get(REQUEST_ID_HEADER).unwrap().as_bytes(),
+        b"caf\xe9"
+    );
+}
+
+#[tokio::test]
+async fn panics_become_json_500s_with_the_request_id() {
+    use axum::{body::Body, routing::get, Router};
+    // for Body::collect
+    use http_body_util::BodyExt;
+    /// for ServiceExt::oneshot
+    use tower::util::ServiceExt;
+
+    install_panic_hook();
+    let (logs, _subscriber) = CapturedLogs::install();
+
+    let app = with_defaults(Router::new().route("/", get(|| async { panic!("boom") })));
+
+    let response = app
+        .oneshot(
+            axum::http::Request::builder()
+                .uri("/")
+                .header(REQUEST_ID_HEADER, "abc-123")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(
+        response.headers().get(REQUEST_ID_HEADER).unwrap(),
+        "abc-123"
+    );
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(
+        body,
+        serde_json::json!({ "error": "internal server error", "request_id": "abc-123" })
+    );
+
+    let logs = logs.contents();
+
+    assert!(logs.contains("Handler panicked: boom"));
+    assert!(logs.contains("request_id=\"abc-123\""));
+    assert!(logs.contains("location=src/server.rs:"));
+    assert!(logs.contains("backtrace="));
+}
+
+#[tokio::test]
+async fn panics_caught_by_handlers_are_reported_once() {
+    use axum::{body::Body, http::Request, routing::get, Router};
+    /// for ServiceExt::oneshot
+    use tower::util::ServiceExt;
+
+    install_panic_hook();
+    let (logs, _subscriber) = CapturedLogs::install();
+
+    let app = with_defaults(
+        Router::new()
+            .route(
+                "/caught",
+                get(|| async {
+                    let _ = std::panic::catch_unwind(|| panic!("caught inside"));
+                }),
+            )
+            .route(
+                "/resumed",
+                get(|| async { std::panic::resume_unwind(Box::new("resumed")) }),
+            ),
+    );
+
+    for uri in ["/caught", "/resumed"] {
+        app.clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+    }
+
+    let logs = logs.contents();
+
+    assert!(logs.contains("Handler caught a panic of its own: caught inside"));
+
+    // resume_unwind skips the hook, so nothing was recorded for it; the
+    // earlier, already reported panic must not be passed off as its cause
+    let resumed = logs
+        .lines()
+        .find(|line| line.contains("Handler panicked: resumed"))
+        .unwrap();
+
+    assert!(resumed.contains("location=unknown"), "{resumed}");
+}
+
+#[tokio::test]
+async fn example_routers_get_the_default_layers() {
+    use axum::{body::Body, routing::get};
+    // for Body::collect
+    use http_body_util::BodyExt;
+    /// for ServiceExt::oneshot
+    use tower::util::ServiceExt;
+
+    let routers = [
+        ("hello_world", crate::basics::hello_world_router()),
+        ("example_axum", crate::playground::example_axum_router()),
+        ("cat_fact", crate::client::cat_fact_router()),
+        ("posts", crate::client::posts_router()),
+    ];
+
+    for (name, router) in routers {
+        let app = with_defaults(router.route("/panic", get(|| async { panic!("boom") })));
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/does-not-exist")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{name}");
+        assert!(response.headers().contains_key(REQUEST_ID_HEADER), "{name}");
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/panic")
+                    .header(REQUEST_ID_HEADER, "abc-123")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response.status(),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "{name}"
+        );
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(
+            body,
+            serde_json::json!({ "error": "internal server error", "request_id": "abc-123" }),
+            "{name}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn shutdown_drops_requests_that_outlive_the_timeout() {
+    use axum::routing::get;
+    use std::sync::Arc;
+    use tokio::sync::Notify;
+
+    let started = Arc::new(Notify::new());
+    let app = Router::new().route(
+        "/stuck",
+        get({
+            let started = started.clone();
+            move || async move {
+                started.notify_one();
+                std::future::pending::<()>().await
+            }
+        }),
+    );
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/stuck", listener.local_addr().unwrap());
+
+    let (stop, stopped) = oneshot::channel::<()>();
+    let server = tokio::spawn(serve_until(
+        listener,
+        app,
+        async move {
+            let _ = stopped.await;
+        },
+        Duration::from_millis(100),
+    ));
+
+    tokio::spawn(reqwest::get(url));
+    started.notified().await;
+
+    stop.send(()).unwrap();
+
+    let err = tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .expect("serve did not return after the shutdown timeout")
+        .unwrap()
+        .unwrap_err();
+
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+}
+
+#[tokio::test]
+async fn shutdown_with_nothing_in_flight_is_ok() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+
+    let result = tokio::time::timeout(
+        Duration::from_secs(5),
+        serve_until(
+            listener,
+            Router::new(),
+            std::future::ready(()),
+            Duration::from_secs(60),
+        ),
+    )
+    .await
+    .expect("serve did not return after draining");
+
+    result.unwrap();
+}